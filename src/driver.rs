@@ -0,0 +1,233 @@
+use ggez::{Context, GameError};
+use ggez::glam::*;
+use ggez::graphics::{Canvas, Color, DrawParam, Drawable, ScreenImage, Text};
+
+use crate::{Ball, Meshes, PhysicsContext, canvas_position};
+
+// A Renderer contributes to the drawn frame without controlling when it happens:
+// `record` runs once per physics step (cheap bookkeeping only, no GPU work), `flush`
+// runs once per emitted frame to push any accumulated state onto the GPU (e.g. baking
+// this frame's trail segments into a persistent texture), and `present` draws into the
+// on-screen canvas. Only `present` is required; `record` and `flush` default to doing nothing.
+pub(crate) trait Renderer {
+    fn record(&mut self, _ball: &Ball, _physics_ctx: &PhysicsContext) {}
+
+    fn flush(&mut self, _ctx: &mut Context, _meshes: &Meshes, _physics_ctx: &PhysicsContext) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn present(
+        &mut self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        ball: &Ball,
+        physics_ctx: &PhysicsContext,
+        meshes: &Meshes
+    ) -> Result<(), GameError>;
+}
+
+pub(crate) struct MultiRenderer {
+    renderers: Vec<Box<dyn Renderer>>
+}
+
+impl MultiRenderer {
+    pub(crate) fn new(renderers: Vec<Box<dyn Renderer>>) -> MultiRenderer {
+        MultiRenderer { renderers }
+    }
+
+    fn record(&mut self, ball: &Ball, physics_ctx: &PhysicsContext) {
+        for renderer in self.renderers.iter_mut() {
+            renderer.record(ball, physics_ctx);
+        }
+    }
+
+    fn flush(&mut self, ctx: &mut Context, meshes: &Meshes, physics_ctx: &PhysicsContext) -> Result<(), GameError> {
+        for renderer in self.renderers.iter_mut() {
+            renderer.flush(ctx, meshes, physics_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    fn present(&mut self, ctx: &mut Context, ball: &Ball, physics_ctx: &PhysicsContext, meshes: &Meshes) -> Result<(), GameError> {
+        let mut canvas = Canvas::from_frame(ctx, Color::WHITE);
+        for renderer in self.renderers.iter_mut() {
+            renderer.present(&mut canvas, ctx, ball, physics_ctx, meshes)?;
+        }
+        canvas.finish(&mut ctx.gfx)?;
+
+        Ok(())
+    }
+}
+
+// trail overlay: accumulates each step's position into a persistent off-screen texture
+// so the trail survives across frames, then blits that texture behind the live ball marker
+pub(crate) struct TrailRenderer {
+    trail: ScreenImage,
+    pending: Vec<Vec2>
+}
+
+impl TrailRenderer {
+    pub(crate) fn new(ctx: &mut Context) -> TrailRenderer {
+        TrailRenderer {
+            trail: ScreenImage::new(&ctx.gfx, None, 1., 1., 1),
+            pending: vec![]
+        }
+    }
+}
+
+impl Renderer for TrailRenderer {
+    fn record(&mut self, ball: &Ball, _physics_ctx: &PhysicsContext) {
+        self.pending.push(ball.pos.xy());
+    }
+
+    fn flush(&mut self, ctx: &mut Context, meshes: &Meshes, physics_ctx: &PhysicsContext) -> Result<(), GameError> {
+        let mut trail_canvas = Canvas::from_screen_image(ctx, &mut self.trail, None);
+        for pos in self.pending.drain(..).map(|pos| canvas_position(pos, ctx, physics_ctx)) {
+            trail_canvas.draw(&meshes.trail, pos);
+        }
+        trail_canvas.finish(&mut ctx.gfx)?;
+
+        Ok(())
+    }
+
+    fn present(
+        &mut self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        ball: &Ball,
+        physics_ctx: &PhysicsContext,
+        meshes: &Meshes
+    ) -> Result<(), GameError> {
+        self.trail.image(&ctx.gfx).draw(canvas, DrawParam::new());
+        canvas.draw(&meshes.ball, canvas_position(ball.pos.xy(), ctx, physics_ctx));
+
+        Ok(())
+    }
+}
+
+// live basin preview: tints each magnet marker with its own color; it does not mark
+// which magnet the bob is currently headed for
+pub(crate) struct BasinPreviewRenderer;
+
+impl BasinPreviewRenderer {
+    pub(crate) fn new() -> BasinPreviewRenderer {
+        BasinPreviewRenderer
+    }
+}
+
+impl Renderer for BasinPreviewRenderer {
+    fn present(
+        &mut self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        ball: &Ball,
+        physics_ctx: &PhysicsContext,
+        meshes: &Meshes
+    ) -> Result<(), GameError> {
+        for magnet in ball.magnets.iter() {
+            let dest = canvas_position(magnet.position.xy(), ctx, physics_ctx);
+            let color = Color::from((magnet.color.0[0], magnet.color.0[1], magnet.color.0[2]));
+            canvas.draw(&meshes.magnet, DrawParam::new().dest(dest).color(color));
+        }
+
+        Ok(())
+    }
+}
+
+// measurement HUD: elapsed simulated time and the bob's kinetic plus gravitational
+// potential energy; the magnets' potential energy is not included
+pub(crate) struct MeasurementHud {
+    elapsed_steps: u64
+}
+
+impl MeasurementHud {
+    pub(crate) fn new() -> MeasurementHud {
+        MeasurementHud { elapsed_steps: 0 }
+    }
+}
+
+impl Renderer for MeasurementHud {
+    fn record(&mut self, _ball: &Ball, _physics_ctx: &PhysicsContext) {
+        self.elapsed_steps += 1;
+    }
+
+    fn present(
+        &mut self,
+        canvas: &mut Canvas,
+        _ctx: &mut Context,
+        ball: &Ball,
+        physics_ctx: &PhysicsContext,
+        _meshes: &Meshes
+    ) -> Result<(), GameError> {
+        let elapsed = self.elapsed_steps as f32 * physics_ctx.time_precision;
+        let kinetic = 0.5 * ball.mass * ball.velocity.length_squared();
+        let potential = ball.mass * physics_ctx.gravity * (ball.pos.z - ball.rope_pivot.z);
+        let energy = kinetic + potential;
+
+        let text = Text::new(format!("t = {:.2}s  E = {:.4}J", elapsed, energy));
+        canvas.draw(&text, DrawParam::new().dest(vec2(10., 10.)).color(Color::BLACK));
+
+        Ok(())
+    }
+}
+
+// owns the simulation and its renderers, and keeps physics substeps independent of
+// frame emission: `step` always advances the physics, but only flushes the renderers
+// every `steps_per_frame` calls, so substep count can be cranked up for accuracy
+// without slowing the window down; `present` draws the current state and is not
+// gated by `step`
+pub(crate) struct Driver {
+    ball: Ball,
+    physics_ctx: PhysicsContext,
+    meshes: Meshes,
+    steps_per_frame: u32,
+    steps_since_frame: u32,
+    renderer: MultiRenderer
+}
+
+impl Driver {
+    pub(crate) fn new(
+        ball: Ball,
+        physics_ctx: PhysicsContext,
+        meshes: Meshes,
+        steps_per_frame: u32,
+        renderer: MultiRenderer
+    ) -> Driver {
+        Driver {
+            ball,
+            physics_ctx,
+            meshes,
+            steps_per_frame,
+            steps_since_frame: 0,
+            renderer
+        }
+    }
+
+    pub(crate) fn physics_ctx(&self) -> &PhysicsContext {
+        &self.physics_ctx
+    }
+
+    pub(crate) fn reset(&mut self, pos: Vec2) {
+        self.ball.pos = Ball::initial_pos(pos, self.ball.rope_pivot, self.ball.rope_len);
+        self.ball.velocity = vec3(0., 0., 0.);
+        self.steps_since_frame = 0;
+    }
+
+    pub(crate) fn step(&mut self, ctx: &mut Context) -> Result<(), GameError> {
+        self.ball.move_step(&self.physics_ctx);
+        self.renderer.record(&self.ball, &self.physics_ctx);
+
+        self.steps_since_frame += 1;
+        if self.steps_since_frame >= self.steps_per_frame {
+            self.steps_since_frame = 0;
+            self.renderer.flush(ctx, &self.meshes, &self.physics_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn present(&mut self, ctx: &mut Context) -> Result<(), GameError> {
+        self.renderer.present(ctx, &self.ball, &self.physics_ctx, &self.meshes)
+    }
+}
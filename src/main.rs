@@ -1,3 +1,6 @@
+mod driver;
+mod scene;
+
 use std::path::Path;
 
 use ggez::*;
@@ -5,8 +8,12 @@ use ggez::glam::*;
 use ggez::graphics::*;
 
 use image::{RgbImage, Rgb};
+use rayon::prelude::*;
+
+use driver::{Driver, MultiRenderer, TrailRenderer, BasinPreviewRenderer, MeasurementHud};
+use scene::Scene;
 
-fn magnet_circle(colors: Vec<Rgb<u8>>, radius: f32, height: f32, angle_delta: f32) -> Vec<Magnet> {
+pub(crate) fn magnet_circle(colors: Vec<Rgb<u8>>, radius: f32, height: f32, angle_delta: f32) -> Vec<Magnet> {
     let amount = colors.len();
     let single_angle_change = 360. / (amount as f32);
     colors.into_iter().enumerate().map(|(i, color)| {
@@ -24,7 +31,7 @@ fn magnet_circle(colors: Vec<Rgb<u8>>, radius: f32, height: f32, angle_delta: f3
     }).collect()
 }
 
-fn canvas_position(pos: Vec2, ctx: &mut Context, physics_ctx: &PhysicsContext) -> Vec2 {
+pub(crate) fn canvas_position(pos: Vec2, ctx: &mut Context, physics_ctx: &PhysicsContext) -> Vec2 {
     let center: Vec2 = ctx.gfx.size().into();
     let center = center / 2.;
     center + pos * physics_ctx.pixels_per_meter
@@ -40,120 +47,185 @@ fn world_position_no_ctx(pos: Vec2, center: Vec2, physics_ctx: &PhysicsContext)
     (pos - center) / physics_ctx.pixels_per_meter
 }
 
-fn angle3(x1: Vec3, x2: Vec3) -> f32 {
-    (x1.dot(x2) * x1.length_recip() * x2.length_recip()).acos()
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Integrator {
+    Euler,
+    Rk4,
 }
 
-struct PhysicsContext {
-    gravity: f32,
+pub(crate) struct PhysicsContext {
+    pub(crate) gravity: f32,
     pixels_per_meter: f32,
     magnet_coefficent: f32,
-    time_precision: f32,
-    speed: f32
+    pub(crate) time_precision: f32,
+    speed: f32,
+    integrator: Integrator
 }
 
 impl PhysicsContext {
     fn new() -> PhysicsContext {
-        PhysicsContext { 
+        PhysicsContext {
             gravity: 10.,
-            pixels_per_meter: 3000., 
+            pixels_per_meter: 3000.,
             magnet_coefficent: 0.0001,
             time_precision: 0.001,
-            speed: 1.
+            speed: 1.,
+            integrator: Integrator::Euler
+        }
+    }
+
+    fn from_scene(scene: &Scene) -> PhysicsContext {
+        PhysicsContext {
+            gravity: scene.gravity,
+            pixels_per_meter: 1. / scene.meters_per_unit,
+            magnet_coefficent: scene.magnet_coefficent,
+            time_precision: scene.time_step,
+            speed: 1.,
+            integrator: Integrator::Euler
         }
     }
 }
 
 #[derive(Clone, Copy)]
-struct Magnet {
-    position: Vec3,
-    color: Rgb<u8>
+pub(crate) struct Magnet {
+    pub(crate) position: Vec3,
+    pub(crate) color: Rgb<u8>
 }
 
-struct Ball {
-    mass: f32,
-    pos: Vec2,
-    rope_len: f32,
-    rope_pivot: Vec3,
-    velocity: Vec3,
+#[derive(Clone)]
+pub(crate) struct Ball {
+    pub(crate) mass: f32,
+    pub(crate) pos: Vec3,
+    pub(crate) rope_len: f32,
+    pub(crate) rope_pivot: Vec3,
+    pub(crate) velocity: Vec3,
     air_friction: f32,
-    magnets: Vec<Magnet>,
-    last_positions: Vec<Vec2>,
+    pub(crate) magnets: Vec<Magnet>,
 }
 
 impl Ball {
-    fn ball_height(&self) -> f32 {
-        let a = self.pos.distance(self.rope_pivot.xy());
-        let c = self.rope_len;
-        let b = self.rope_pivot.z - ((c - a) * (c + a)).sqrt();
-        b
+    // height of a bob dropped from rest above `xy`, with the rope taut
+    pub(crate) fn initial_pos(xy: Vec2, rope_pivot: Vec3, rope_len: f32) -> Vec3 {
+        let a = xy.distance(rope_pivot.xy());
+        let c = rope_len;
+        let z = rope_pivot.z - ((c - a) * (c + a)).sqrt();
+        vec3(xy.x, xy.y, z)
     }
 
-    fn move_step(&mut self, physics_ctx: &PhysicsContext) {
-        let ball_pos = vec3(self.pos.x, self.pos.y, self.ball_height());
+    pub(crate) fn from_scene(scene: &Scene, xy: Vec2) -> Ball {
+        let rope_pivot: Vec3 = scene.rope_pivot.into();
+        Ball {
+            mass: scene.mass,
+            pos: Ball::initial_pos(xy, rope_pivot, scene.rope_length),
+            rope_len: scene.rope_length,
+            rope_pivot,
+            velocity: vec3(0., 0., 0.),
+            air_friction: scene.air_resistence_coefficent,
+            magnets: scene.magnets()
+        }
+    }
+
+    // keep the bob on the sphere of radius rope_len centered at rope_pivot:
+    // rescale the position so its offset from the pivot has exactly that length,
+    // and drop the radial component of velocity so it stays tangent to the sphere
+    fn apply_constraint(&mut self) {
+        let n = (self.pos - self.rope_pivot).normalize();
+        self.pos = self.rope_pivot + n * self.rope_len;
+        self.velocity -= self.velocity.dot(n) * n;
+    }
+
+    // gravity + quadratic air drag + summed inverse-square magnet attraction, rope-constrained
+    fn accel(&self, pos: Vec3, velocity: Vec3, physics_ctx: &PhysicsContext) -> Vec3 {
         let gravity_force = vec3(0., 0., -1. * physics_ctx.gravity * self.mass);
-        let friction_force = self.velocity.normalize_or_zero() * self.velocity.length_squared() * self.air_friction * -1.;
+        let friction_force = velocity.normalize_or_zero() * velocity.length_squared() * self.air_friction * -1.;
 
         let mut magnetic_force = vec3(0., 0., 0.);
         for magnet in self.magnets.iter() {
-            let magnet_force = magnet.position - ball_pos; 
+            let magnet_force = magnet.position - pos;
             let magnitude = physics_ctx.magnet_coefficent / magnet_force.length_squared();
             let magnet_force = magnet_force.normalize() * magnitude;
 
             magnetic_force += magnet_force;
         }
 
-        let force_vector = gravity_force + magnetic_force + friction_force;
-        let rope_vector = self.rope_pivot - ball_pos;
+        let force = gravity_force + magnetic_force + friction_force;
 
-        let force_projected = (force_vector.dot(rope_vector) / rope_vector.length_squared()) * rope_vector;
-        let angle = angle3(force_projected, force_vector).to_degrees();
-        let force_projected = if angle < 90. {
-            force_projected * -1.
-        } else {
-            force_projected
-        };
+        // this is the rope tension: it exactly cancels the radial component of
+        // every other force, so only the tangential component accelerates the bob
+        let n = (pos - self.rope_pivot).normalize();
+        let tangential_force = force - force.dot(n) * n;
+        tangential_force / self.mass
+    }
 
-        let force = force_vector + force_projected;
+    // y = (pos, vel), f(y) = (vel, accel(pos, vel))
+    fn derivative(&self, pos: Vec3, velocity: Vec3, physics_ctx: &PhysicsContext) -> (Vec3, Vec3) {
+        (velocity, self.accel(pos, velocity, physics_ctx))
+    }
 
-        let a = force / self.mass;
-        self.velocity += a * physics_ctx.time_precision;
-        self.pos += self.velocity.xy() * physics_ctx.time_precision;
+    fn move_step_euler(&mut self, physics_ctx: &PhysicsContext) {
+        let dt = physics_ctx.time_precision;
+        let a = self.accel(self.pos, self.velocity, physics_ctx);
+        self.velocity += a * dt;
+        self.pos += self.velocity * dt;
     }
 
-    fn move_over_speed1(&mut self, time_delta: f32, physics_ctx: &PhysicsContext) {
-        let times = (time_delta / physics_ctx.time_precision).floor() as u32;
-        for _ in 0..times {
-            self.move_step(physics_ctx);
-        }
+    fn move_step_rk4(&mut self, physics_ctx: &PhysicsContext) {
+        let dt = physics_ctx.time_precision;
+
+        let (k1_pos, k1_vel) = self.derivative(self.pos, self.velocity, physics_ctx);
+        let (k2_pos, k2_vel) = self.derivative(
+            self.pos + k1_pos * (dt / 2.),
+            self.velocity + k1_vel * (dt / 2.),
+            physics_ctx
+        );
+        let (k3_pos, k3_vel) = self.derivative(
+            self.pos + k2_pos * (dt / 2.),
+            self.velocity + k2_vel * (dt / 2.),
+            physics_ctx
+        );
+        let (k4_pos, k4_vel) = self.derivative(
+            self.pos + k3_pos * dt,
+            self.velocity + k3_vel * dt,
+            physics_ctx
+        );
+
+        self.pos += (k1_pos + k2_pos * 2. + k3_pos * 2. + k4_pos) * (dt / 6.);
+        self.velocity += (k1_vel + k2_vel * 2. + k3_vel * 2. + k4_vel) * (dt / 6.);
     }
 
-    fn move_over_time(&mut self, time_delta: f32, physics_ctx: &PhysicsContext) {
-        let times = (time_delta * physics_ctx.speed / physics_ctx.time_precision).floor() as u32;
-        for _ in 0..times {
-            self.move_step(physics_ctx);
+    pub(crate) fn move_step(&mut self, physics_ctx: &PhysicsContext) {
+        match physics_ctx.integrator {
+            Integrator::Euler => self.move_step_euler(physics_ctx),
+            Integrator::Rk4 => self.move_step_rk4(physics_ctx),
         }
+        self.apply_constraint();
     }
 
-    fn move_over_time_save_positions(&mut self, time_delta: f32, physics_ctx: &PhysicsContext) {
-        let times = (time_delta * physics_ctx.speed / physics_ctx.time_precision).floor() as u32;
-        let positions: Vec<_> = (0..times).map(|_| {
-            self.move_step(physics_ctx);
-            self.pos.clone()
-        }).collect();
+    // index of, and distance to, the magnet closest to the bob in the xy plane
+    fn nearest_magnet(&self) -> (usize, f32) {
+        let pos = self.pos.xy();
+        let mut closest = 0;
+        let mut min_distance = pos.distance(self.magnets[0].position.xy());
+        for (i, magnet) in self.magnets.iter().enumerate().skip(1) {
+            let d = pos.distance(magnet.position.xy());
+            if d < min_distance {
+                closest = i;
+                min_distance = d;
+            }
+        }
 
-        self.last_positions = positions;
+        (closest, min_distance)
     }
 }
 
-struct Meshes {
-    ball: Mesh,
-    magnet: Mesh,
-    trail: Mesh,
+pub(crate) struct Meshes {
+    pub(crate) ball: Mesh,
+    pub(crate) magnet: Mesh,
+    pub(crate) trail: Mesh,
 }
 
 impl Meshes {
-    fn new(ctx: &mut Context) -> Meshes {
+    pub(crate) fn new(ctx: &mut Context) -> Meshes {
         Meshes {
             ball: Mesh::new_circle(
                 &ctx.gfx,
@@ -179,89 +251,78 @@ impl Meshes {
     }
 }
 
+fn new_ball(pos: Vec2, rope_pivot: Vec3, rope_len: f32) -> Ball {
+    Ball {
+        // r = 0.02 of iron
+        mass: 0.264,
+        pos: Ball::initial_pos(pos, rope_pivot, rope_len),
+        rope_len,
+        rope_pivot,
+        velocity: vec3(0., 0., 0.),
+        air_friction: 0.037,
+        magnets: magnet_circle(
+            vec![
+                Rgb([0, 0, 0]),
+                Rgb([0, 0, 0]),
+                Rgb([0, 0, 0]),
+            ],
+            0.04,
+            0.03,
+            30.
+        )
+    }
+}
+
 struct State {
-    trail: ScreenImage,
-    ball: Ball,
-    meshes: Meshes,
-    physics_ctx: PhysicsContext
+    driver: Driver
 }
 
 impl State {
     fn new(pos: Vec2, ctx: &mut Context) -> State {
+        let rope_len = 0.3;
+        let rope_pivot = vec3(0., 0., 0.33);
+
+        let ball = new_ball(pos, rope_pivot, rope_len);
+        let meshes = Meshes::new(ctx);
+        let renderer = MultiRenderer::new(vec![
+            Box::new(TrailRenderer::new(ctx)),
+            Box::new(BasinPreviewRenderer::new()),
+            Box::new(MeasurementHud::new()),
+        ]);
+
         State {
-            trail: ScreenImage::new(
-                &ctx.gfx, 
-                None, 
-                1., 
-                1., 
-                1
-            ),
-            ball: Ball {
-                // r = 0.02 of iron
-                mass: 0.264,
-                pos,
-                rope_len: 0.3,
-                rope_pivot: vec3(0., 0., 0.33),
-                velocity: vec3(0., 0., 0.),
-                air_friction: 0.037,
-                magnets: magnet_circle(
-                    vec![
-                        Rgb([0, 0, 0]),
-                        Rgb([0, 0, 0]),
-                        Rgb([0, 0, 0]),
-                    ], 
-                    0.04, 
-                    0.03, 
-                    30.
-                ),
-                last_positions: vec![]
-            },
-            meshes: Meshes::new(ctx),
-            physics_ctx: PhysicsContext::new()
+            driver: Driver::new(ball, PhysicsContext::new(), meshes, 1, renderer)
         }
     }
-
-    fn update(&mut self, ctx: &mut Context) {
-        self.ball.move_over_time_save_positions(ctx.time.delta().as_secs_f32(), &self.physics_ctx);
-    }
 }
 
 impl ggez::event::EventHandler<GameError> for State {
     fn update(&mut self, ctx: &mut Context) -> Result<(), GameError> {
         if ctx.mouse.button_just_pressed(event::MouseButton::Left) {
-            *self = State::new(world_position(ctx.mouse.position().into(), ctx, &self.physics_ctx), ctx);
+            let pos = world_position(ctx.mouse.position().into(), ctx, self.driver.physics_ctx());
+            self.driver.reset(pos);
         }
-        self.update(ctx);
-
-        Ok(())
-    }
-
-    fn draw(&mut self, ctx: &mut Context) -> Result<(), GameError> { 
-        let mut trail_canvas = Canvas::from_screen_image(ctx, &mut self.trail, None);
 
-        let mut last_pos = vec2(0., 0.);
-        for pos in self.ball.last_positions.drain(0..).map(|x| canvas_position(x, ctx, &self.physics_ctx)) {
-            trail_canvas.draw(&self.meshes.trail, pos);
-            last_pos = pos;
+        let physics_ctx = self.driver.physics_ctx();
+        let steps = (ctx.time.delta().as_secs_f32() * physics_ctx.speed / physics_ctx.time_precision).floor() as u32;
+        for _ in 0..steps {
+            self.driver.step(ctx)?;
         }
 
-        trail_canvas.finish(&mut ctx.gfx)?;
-
-        let mut canvas = Canvas::from_frame(ctx, Color::WHITE);
-        self.trail.image(&mut ctx.gfx).draw(&mut canvas, DrawParam::new());
-        for magnet in self.ball.magnets.iter() {
-            canvas.draw(&self.meshes.magnet, canvas_position(magnet.position.xy(), ctx, &self.physics_ctx))
-        }
-        canvas.draw(&self.meshes.ball, last_pos);
-
-        canvas.finish(&mut ctx.gfx)?;
-
         Ok(())
     }
+
+    fn draw(&mut self, ctx: &mut Context) -> Result<(), GameError> {
+        self.driver.present(ctx)
+    }
 }
 
 fn main() {
-    run_create_image();
+    let mut args = std::env::args().skip(1);
+    let scene_path = args.next().unwrap_or_else(|| "scenes/default.ron".to_string());
+    let output_path = args.next().unwrap_or_else(|| "result.png".to_string());
+
+    run_create_image(Path::new(&scene_path), Path::new(&output_path));
     // run_simulation();
 }
 
@@ -276,79 +337,211 @@ fn run_simulation() {
     event::run(ctx, event_loop, state);
 }
 
-fn run_create_image() {
-    let image_size = 2000;
-    let magnets = magnet_circle(
-        vec![
-            Rgb([54, 238, 3]),
-            Rgb([238, 254, 11]),
-            Rgb([255, 150, 31]),
-            Rgb([254, 78, 63])
-        ], 
-        0.04, 
-        0.03, 
-        30.
-    );
+fn run_create_image(scene_path: &Path, output_path: &Path) {
+    let scene = Scene::load(scene_path);
+    let physics_ctx = PhysicsContext::from_scene(&scene);
+    let ball = Ball::from_scene(&scene, vec2(0., 0.));
+    let render_cfg = RenderConfig::new();
 
-    let (ball, physics_ctx) = setup_square_scene(
-        image_size, 
-        0.3, 
-        0.03, 
-        magnets
-    );
+    create_square_image(scene.resolution, ball, &physics_ctx, &render_cfg, output_path);
+}
 
-    create_square_image(image_size, ball, &physics_ctx, Path::new("result.png"));
+#[derive(Clone, Copy)]
+enum ColorMode {
+    // plain nearest-magnet basin color
+    Nearest,
+    // nearest-magnet color, value-shaded by log-scaled steps to convergence
+    StepShaded,
 }
 
-fn setup_square_scene(x: u32, rope_len: f32, min_height: f32, magnets: Vec<Magnet>) -> (Ball, PhysicsContext) {
-    let valid_square_side = 2_f32.sqrt() * rope_len;
-    let pixels_per_meter = 10. * (x as f32) / (valid_square_side);
+struct RenderConfig {
+    color_mode: ColorMode,
+    max_steps: u32,
+    convergence_speed: f32,
+    convergence_radius: f32,
+    convergence_window: u32,
+    // K in a K*K grid of jittered sub-samples per pixel; 1 disables supersampling
+    supersample: u32,
+}
 
-    let mut physics_ctx = PhysicsContext::new();
-    physics_ctx.pixels_per_meter = pixels_per_meter;
-    physics_ctx.time_precision = 0.01;
+impl RenderConfig {
+    fn new() -> RenderConfig {
+        RenderConfig {
+            color_mode: ColorMode::Nearest,
+            max_steps: 300_000,
+            convergence_speed: 0.01,
+            convergence_radius: 0.01,
+            convergence_window: 50,
+            supersample: 1
+        }
+    }
+}
 
-    let ball = Ball {
-        mass: 0.264,
-        pos: vec2(0.25, 0.),
-        rope_len,
-        rope_pivot: vec3(0., 0., rope_len + min_height),
-        velocity: vec3(0., 0., 0.),
-        air_friction: 0.037,
-        magnets,
-        last_positions: vec![]
+const UNDECIDED_COLOR: Rgb<u8> = Rgb([128, 128, 128]);
+
+struct BasinResult {
+    // the magnet the bob settled on, or None if it never settled within max_steps
+    magnet: Option<usize>,
+    steps: u32,
+}
+
+fn simulate_basin(mut ball: Ball, xy: Vec2, physics_ctx: &PhysicsContext, render_cfg: &RenderConfig) -> BasinResult {
+    ball.pos = Ball::initial_pos(xy, ball.rope_pivot, ball.rope_len);
+    ball.velocity = vec3(0., 0., 0.);
+
+    let mut settled_magnet = None;
+    let mut settled_steps = 0;
+
+    for step in 0..render_cfg.max_steps {
+        ball.move_step(physics_ctx);
+
+        let (magnet, distance) = ball.nearest_magnet();
+        let settled = ball.velocity.length() < render_cfg.convergence_speed
+            && distance < render_cfg.convergence_radius;
+
+        if !settled {
+            settled_magnet = None;
+            settled_steps = 0;
+            continue;
+        }
+
+        if settled_magnet == Some(magnet) {
+            settled_steps += 1;
+        } else {
+            settled_magnet = Some(magnet);
+            settled_steps = 1;
+        }
+
+        if settled_steps >= render_cfg.convergence_window {
+            return BasinResult { magnet: Some(magnet), steps: step + 1 };
+        }
+    }
+
+    BasinResult { magnet: None, steps: render_cfg.max_steps }
+}
+
+// brightness of `color` falls off with the log of how long the pixel took to converge,
+// so slow-to-settle boundary regions render as dark filaments
+fn shade_by_steps(color: Rgb<u8>, steps: u32, max_steps: u32) -> Rgb<u8> {
+    let t = (steps as f32 + 1.).ln() / (max_steps as f32 + 1.).ln();
+    let brightness = (1. - t).clamp(0.15, 1.);
+    Rgb(color.0.map(|c| (c as f32 * brightness).round() as u8))
+}
+
+fn basin_color(ball: &Ball, result: BasinResult, render_cfg: &RenderConfig) -> Rgb<u8> {
+    let magnet = match result.magnet {
+        Some(magnet) => magnet,
+        None => return UNDECIDED_COLOR,
     };
 
-    (ball, physics_ctx)
+    match render_cfg.color_mode {
+        ColorMode::Nearest => ball.magnets[magnet].color,
+        ColorMode::StepShaded => shade_by_steps(ball.magnets[magnet].color, result.steps, render_cfg.max_steps),
+    }
 }
 
-fn create_square_image(x: u32, ball: Ball, physics_ctx: &PhysicsContext, path: &Path) {
+// lowbias32 xorshift-multiply integer hash, used to derive jitter offsets without a rand dependency
+fn hash_u32(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+fn sample_seed(px: u32, py: u32, i: u32, j: u32, salt: u32) -> u32 {
+    let s = hash_u32(px);
+    let s = hash_u32(s ^ py.wrapping_mul(0x9E3779B9));
+    let s = hash_u32(s ^ i.wrapping_mul(0x85EBCA6B));
+    let s = hash_u32(s ^ j.wrapping_mul(0xC2B2AE35));
+    hash_u32(s ^ salt)
+}
+
+// pseudo-random float in [0, 1), deterministic per seed so renders stay reproducible
+fn jitter(seed: u32) -> f32 {
+    // top 24 bits fit an f32 exactly, so the result never rounds up to 1.0
+    (hash_u32(seed) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let s = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1. / 2.4) - 0.055 };
+    (s.clamp(0., 1.) * 255.).round() as u8
+}
+
+// average sub-sample colors in linear RGB, weighted equally; averaging in sRGB
+// space would darken pixels that straddle two basins
+fn blend_linear(colors: &[Rgb<u8>]) -> Rgb<u8> {
+    let mut sum = [0f32; 3];
+    for color in colors {
+        for (c, s) in color.0.iter().zip(sum.iter_mut()) {
+            *s += srgb_to_linear(*c);
+        }
+    }
+    let n = colors.len() as f32;
+    Rgb(sum.map(|s| linear_to_srgb(s / n)))
+}
+
+// `(px, py)` is the output pixel; at supersample 1 this samples the pixel's own
+// world-space position exactly as before. Above that, it launches a K*K grid of
+// jittered sub-samples across the pixel's world-space cell and blends their
+// settled magnet colors.
+fn pixel_color(ball: Ball, px: u32, py: u32, center: Vec2, physics_ctx: &PhysicsContext, render_cfg: &RenderConfig) -> Rgb<u8> {
+    let k = render_cfg.supersample.max(1);
+    if k == 1 {
+        let xy = world_position_no_ctx(vec2(px as f32, py as f32), center, physics_ctx);
+        let result = simulate_basin(ball.clone(), xy, physics_ctx, render_cfg);
+        return basin_color(&ball, result, render_cfg);
+    }
+
+    let mut samples = Vec::with_capacity((k * k) as usize);
+    for i in 0..k {
+        for j in 0..k {
+            let jx = jitter(sample_seed(px, py, i, j, 0));
+            let jy = jitter(sample_seed(px, py, i, j, 1));
+            let sub_x = px as f32 + (i as f32 + jx) / k as f32;
+            let sub_y = py as f32 + (j as f32 + jy) / k as f32;
+
+            let xy = world_position_no_ctx(vec2(sub_x, sub_y), center, physics_ctx);
+            let result = simulate_basin(ball.clone(), xy, physics_ctx, render_cfg);
+            samples.push(basin_color(&ball, result, render_cfg));
+        }
+    }
+
+    blend_linear(&samples)
+}
+
+fn create_square_image(
+    x: u32,
+    ball: Ball,
+    physics_ctx: &PhysicsContext,
+    render_cfg: &RenderConfig,
+    path: &Path
+) {
     let (w, h) = (x, x);
     let center = vec2(w as f32 / 2., h as f32 / 2.);
-    let mut img = RgbImage::new(w, h);
 
-    let mut ball = ball;
-
-    for x in 0..w {
-        for y in 0..h {
-            let pos = world_position_no_ctx(vec2(x as f32, y as f32), center, &physics_ctx);
-            ball.pos = pos;
-            ball.velocity = vec3(0., 0., 0.);
-            ball.move_over_speed1(30., &physics_ctx);
-
-            let end_pos = ball.pos;
-
-            let mut closest_magnet = 0;
-            let mut min_distance = end_pos.distance(ball.magnets[0].position.xy()); 
-            for (i, magnet_pos) in ball.magnets.iter().enumerate().skip(1) {
-                let d = end_pos.distance(magnet_pos.position.xy());
-                if d < min_distance {
-                    closest_magnet = i;
-                    min_distance = d;
-                }
-            }
+    // each row is independent, so hand rows out to a worker per core and
+    // assemble the image afterward; RgbImage::put_pixel isn't safe to call concurrently
+    let rows: Vec<Vec<Rgb<u8>>> = (0..h)
+        .into_par_iter()
+        .map(|y| {
+            let row_ball = ball.clone();
+            (0..w)
+                .map(|x| pixel_color(row_ball.clone(), x, y, center, physics_ctx, render_cfg))
+                .collect()
+        })
+        .collect();
 
-            img.put_pixel(x, y, ball.magnets[closest_magnet].color)
+    let mut img = RgbImage::new(w, h);
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, color) in row.into_iter().enumerate() {
+            img.put_pixel(x as u32, y as u32, color);
         }
     }
 
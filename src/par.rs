@@ -9,6 +9,12 @@ struct Magnet {
     color: Rgb<u8>
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Integrator {
+    Euler,
+    Rk4,
+}
+
 struct Context {
     gravity: f64,
     mass: f64,
@@ -18,7 +24,8 @@ struct Context {
     magnet_coefficent: f64,
     time_step: f64,
     magnets: Vec<Magnet>,
-    meters_per_unit: f64
+    meters_per_unit: f64,
+    integrator: Integrator
 }
 
 struct State<'a> {
@@ -56,9 +63,14 @@ impl<'a> State<'a> {
     }
 
     fn run_step(&mut self) {
-        take_step(&mut self.position, &mut self.velocity, &self.ctx)
+        match self.ctx.integrator {
+            Integrator::Euler => take_step_euler(&mut self.position, &mut self.velocity, self.ctx),
+            Integrator::Rk4 => take_step_rk4(&mut self.position, &mut self.velocity, self.ctx),
+        }
     }
 
+    // every pixel's state already lives in one Array3 and each step advances the whole
+    // grid in a single vectorized pass, so there's no per-pixel loop left to hand to rayon here
     fn run(&mut self, seconds: f64) {
         let steps = (seconds / self.ctx.time_step).floor() as u32;
         for _ in 0..steps { 
@@ -141,11 +153,28 @@ fn normalize(array: &mut Array3<f64>) {
     array.mapv_inplace(|x| if x.is_nan() { 0. } else { x });
 }
 
-fn take_step(
-    position: &mut Array3<f64>, 
-    velocity: &mut Array3<f64>, 
-    ctx: &Context
-) {
+fn rope_normal(position: &Array3<f64>, ctx: &Context) -> Array3<f64> {
+    let shape = position.shape();
+    let rope_pivot_array = vector3_matrix(shape[0], shape[1], ctx.rope_pivot);
+    let mut n = position - &rope_pivot_array;
+    normalize(&mut n);
+    n
+}
+
+// keep every grid point on the sphere of radius rope_length centered at rope_pivot:
+// rescale the position so its offset from the pivot has exactly that length, and drop
+// the radial component of velocity so it stays tangent to the sphere
+fn apply_constraint(position: &mut Array3<f64>, velocity: &mut Array3<f64>, ctx: &Context) {
+    let shape = position.shape();
+    let rope_pivot_array = vector3_matrix(shape[0], shape[1], ctx.rope_pivot);
+    let n = rope_normal(position, ctx);
+
+    *position = &rope_pivot_array + &n * ctx.rope_length;
+    *velocity = &*velocity - &n * as_uniform_vector(&dot(velocity, &n), 3);
+}
+
+// gravity + quadratic air drag + summed inverse-square magnet attraction, rope-constrained
+fn accel(position: &Array3<f64>, velocity: &Array3<f64>, ctx: &Context) -> Array3<f64> {
     let shape = position.shape();
 
     let mut gravity_force = Array::<f64, _>::zeros((
@@ -163,7 +192,7 @@ fn take_step(
     for magnet in ctx.magnets.iter() {
         let magnet_position_array = vector3_matrix(shape[0], shape[1], magnet.position);
 
-        let mut magnetic_force = &magnet_position_array - &*position;
+        let mut magnetic_force = &magnet_position_array - position;
         let magnitude = ctx.magnet_coefficent / vector_squared_lengths(&magnetic_force);
         normalize(&mut magnetic_force);
         magnetic_force = &magnetic_force * magnitude;
@@ -171,33 +200,54 @@ fn take_step(
     }
 
     let force_vectors = gravity_force + air_resistence_force + total_magnetic_force;
-    let rope_pivot_array = vector3_matrix(shape[0], shape[1], ctx.rope_pivot);
-    let rope_vectors = &rope_pivot_array - &*position;
-    // forces projected onto the normal of the movement plane
-    let forces_projected = 
-        as_uniform_vector(&dot(&force_vectors, &rope_vectors), 3) / 
-        vector_squared_lengths(&rope_vectors) 
-        * -1.
-        * rope_vectors;
-
-    let final_force = force_vectors + forces_projected;
-    let a = final_force / ctx.mass;
+
+    // this is the rope tension: it exactly cancels the radial component of every
+    // other force, so only the tangential component accelerates the bob
+    let n = rope_normal(position, ctx);
+    let tangential_force = &force_vectors - &n * as_uniform_vector(&dot(&force_vectors, &n), 3);
+
+    tangential_force / ctx.mass
+}
+
+fn take_step_euler(
+    position: &mut Array3<f64>,
+    velocity: &mut Array3<f64>,
+    ctx: &Context
+) {
+    let a = accel(position, velocity, ctx);
     *velocity = &*velocity + a * ctx.time_step;
     *position = &*position + &*velocity * ctx.time_step;
+    apply_constraint(position, velocity, ctx);
+}
+
+fn take_step_rk4(
+    position: &mut Array3<f64>,
+    velocity: &mut Array3<f64>,
+    ctx: &Context
+) {
+    let dt = ctx.time_step;
+
+    let k1_pos = velocity.clone();
+    let k1_vel = accel(position, velocity, ctx);
+
+    let pos2 = &*position + &k1_pos * (dt / 2.);
+    let vel2 = &*velocity + &k1_vel * (dt / 2.);
+    let k2_pos = vel2.clone();
+    let k2_vel = accel(&pos2, &vel2, ctx);
 
-    // fix position y
-    let mut position2d = position.clone();
-    position2d.remove_index(Axis(2), 2);
-    let mut rope_pivot_array2d = rope_pivot_array.clone();
-    rope_pivot_array2d.remove_index(Axis(2), 2);
+    let pos3 = &*position + &k2_pos * (dt / 2.);
+    let vel3 = &*velocity + &k2_vel * (dt / 2.);
+    let k3_pos = vel3.clone();
+    let k3_vel = accel(&pos3, &vel3, ctx);
 
-    let a = position2d - rope_pivot_array2d;
-    let a = a.sum_axis(Axis(2));
-    let c = ctx.rope_length;
-    let b = ctx.rope_pivot.z - ((&a + c) * (a - c)).mapv(f64::sqrt);
+    let pos4 = &*position + &k3_pos * dt;
+    let vel4 = &*velocity + &k3_vel * dt;
+    let k4_pos = vel4.clone();
+    let k4_vel = accel(&pos4, &vel4, ctx);
 
-    position.remove_index(Axis(2), 2);
-    *position = concatenate![Axis(2), position.view(), b.insert_axis(Axis(2))];
+    *position = &*position + (k1_pos + k2_pos * 2. + k3_pos * 2. + k4_pos) * (dt / 6.);
+    *velocity = &*velocity + (k1_vel + k2_vel * 2. + k3_vel * 2. + k4_vel) * (dt / 6.);
+    apply_constraint(position, velocity, ctx);
 }
 
 pub fn run() {
@@ -223,7 +273,8 @@ pub fn run() {
                 color: Rgb([255, 255, 0])
             }
         ],
-        meters_per_unit: 0.001
+        meters_per_unit: 0.001,
+        integrator: Integrator::Euler
     };
     
     let mut state = State::new(200, 200, &ctx);
@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::Path;
+
+use image::Rgb;
+use serde::Deserialize;
+
+use crate::{magnet_circle, Magnet};
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+enum MagnetSpec {
+    Single {
+        position: [f32; 3],
+        color: [u8; 3],
+    },
+    Circle {
+        colors: Vec<[u8; 3]>,
+        radius: f32,
+        height: f32,
+        angle_delta: f32,
+    },
+}
+
+impl MagnetSpec {
+    fn into_magnets(self) -> Vec<Magnet> {
+        match self {
+            MagnetSpec::Single { position, color } => vec![Magnet {
+                position: position.into(),
+                color: Rgb(color)
+            }],
+            MagnetSpec::Circle { colors, radius, height, angle_delta } => {
+                magnet_circle(colors.into_iter().map(Rgb).collect(), radius, height, angle_delta)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Scene {
+    pub(crate) gravity: f32,
+    pub(crate) mass: f32,
+    pub(crate) rope_length: f32,
+    pub(crate) rope_pivot: [f32; 3],
+    pub(crate) air_resistence_coefficent: f32,
+    pub(crate) magnet_coefficent: f32,
+    pub(crate) time_step: f32,
+    pub(crate) meters_per_unit: f32,
+    pub(crate) resolution: u32,
+    magnets: Vec<MagnetSpec>
+}
+
+impl Scene {
+    pub(crate) fn load(path: &Path) -> Scene {
+        let contents = fs::read_to_string(path).expect("failed to read scene file");
+        ron::from_str(&contents).expect("failed to parse scene file")
+    }
+
+    pub(crate) fn magnets(&self) -> Vec<Magnet> {
+        self.magnets.iter().cloned().flat_map(MagnetSpec::into_magnets).collect()
+    }
+}